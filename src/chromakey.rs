@@ -0,0 +1,75 @@
+/// How the keyed-out background should be replaced once the detected
+/// green-screen color has been chroma-keyed out.
+pub enum ChromaAction {
+    /// Emit an alpha-channel VP9 stream so the background is simply absent.
+    Transparent,
+    /// Composite over a solid color (`0xRRGGBB`).
+    Color(String),
+    /// Composite over a still image at this local path.
+    Image(String),
+}
+
+/// Everything `convert_to_webm` needs to turn a detected green-screen color
+/// into ffmpeg filter arguments.
+pub struct ChromaKeyPlan {
+    pub color: String,
+    pub similarity: f32,
+    pub blend: f32,
+    pub action: ChromaAction,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl ChromaKeyPlan {
+    /// Builds the extra ffmpeg arguments needed to key `self.color` out of
+    /// stream `0:v` and replace it per `self.action`. Returns the arg list
+    /// plus an optional extra input file ffmpeg should also read.
+    pub fn ffmpeg_args(&self) -> (Vec<String>, Option<String>) {
+        match &self.action {
+            ChromaAction::Transparent => (
+                vec![
+                    "-vf".to_string(),
+                    format!(
+                        "chromakey={}:similarity={}:blend={}",
+                        self.color, self.similarity, self.blend
+                    ),
+                    "-pix_fmt".to_string(),
+                    "yuva420p".to_string(),
+                    "-auto-alt-ref".to_string(),
+                    "0".to_string(),
+                ],
+                None,
+            ),
+            ChromaAction::Color(bg_hex) => (
+                vec![
+                    "-filter_complex".to_string(),
+                    format!(
+                        "color=c={}:s={}x{}[bg];[0:v]chromakey={}:similarity={}:blend={}[fg];\
+                         [bg][fg]overlay=shortest=1,format=yuv420p[outv]",
+                        bg_hex, self.width, self.height, self.color, self.similarity, self.blend
+                    ),
+                    "-map".to_string(),
+                    "[outv]".to_string(),
+                    "-map".to_string(),
+                    "0:a?".to_string(),
+                ],
+                None,
+            ),
+            ChromaAction::Image(path) => (
+                vec![
+                    "-filter_complex".to_string(),
+                    format!(
+                        "[1:v]scale={}:{}[bg];[0:v]chromakey={}:similarity={}:blend={}[fg];\
+                         [bg][fg]overlay=shortest=1,format=yuv420p[outv]",
+                        self.width, self.height, self.color, self.similarity, self.blend
+                    ),
+                    "-map".to_string(),
+                    "[outv]".to_string(),
+                    "-map".to_string(),
+                    "0:a?".to_string(),
+                ],
+                Some(path.clone()),
+            ),
+        }
+    }
+}
@@ -1,15 +1,23 @@
+mod chromakey;
+mod metrics;
+mod probe;
+mod queue;
+mod reaper;
+mod store;
+
 use actix_multipart::Multipart;
 use actix_web::{web, App, HttpResponse, HttpServer, Result};
 use futures_util::stream::StreamExt;
+use queue::JobQueue;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
-use std::path::Path;
+use std::io;
 use std::process::Stdio;
-use tempfile::NamedTempFile;
+use std::sync::Arc;
+use std::time::Instant;
+use store::{ByteStream, Store};
 use tokio::process::Command;
 use tokio::time::{sleep, Duration};
 use uuid::Uuid;
-use std::time::Instant;
 
 #[derive(Serialize)]
 struct HealthResponse {
@@ -21,33 +29,64 @@ struct HealthResponse {
 struct UploadResponse {
     job_id: String,
     status: String,
+    queue_position: Option<usize>,
+    delete_token: String,
 }
 
 #[derive(Serialize)]
 struct StatusResponse {
     status: String,
     progress: u8,
+    queue_position: Option<usize>,
     detected_green: Option<String>,
+    chroma_key_applied: bool,
     error: Option<String>,
+    width: Option<i64>,
+    height: Option<i64>,
+    duration: Option<f64>,
+    codec: Option<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
-struct JobStatus {
-    status: String,
-    progress: u8,
-    result_path: Option<String>,
+pub(crate) struct JobStatus {
+    pub(crate) status: String,
+    pub(crate) progress: u8,
+    pub(crate) result_key: Option<String>,
     detected_green: Option<String>,
-    error: Option<String>,
+    #[serde(default)]
+    chroma_key_applied: bool,
+    pub(crate) error: Option<String>,
+    pub(crate) options: ConversionOptions,
+    width: Option<i64>,
+    height: Option<i64>,
+    duration: Option<f64>,
+    codec: Option<String>,
+    pub(crate) delete_token: String,
+    pub(crate) expires_at: Option<u64>,
+    pub(crate) started_at: Option<u64>,
 }
 
-#[derive(Deserialize, Clone)]
-struct ConversionOptions {
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct ConversionOptions {
     #[serde(default = "default_crf")]
     crf: u8,
     #[serde(default = "default_audio_bitrate")]
     audio_bitrate: String,
     #[serde(default)]
     detect_green: bool,
+    /// "transparent" | "color" | "image"; what to do with the detected
+    /// green-screen color once it's keyed out.
+    #[serde(default)]
+    green_action: Option<String>,
+    /// Background color (`0xRRGGBB`) for `green_action = "color"`.
+    #[serde(default)]
+    chroma_color: Option<String>,
+    #[serde(default = "default_chroma_similarity")]
+    chroma_similarity: f32,
+    #[serde(default = "default_chroma_blend")]
+    chroma_blend: f32,
+    #[serde(default)]
+    pub(crate) has_background_image: bool,
 }
 
 fn default_crf() -> u8 {
@@ -58,39 +97,89 @@ fn default_audio_bitrate() -> String {
     "128k".to_string()
 }
 
-fn get_job_path(job_id: &str) -> String {
-    format!("/tmp/jobs/{}.json", job_id)
+fn default_chroma_similarity() -> f32 {
+    0.2
 }
 
-async fn save_job_status(job_id: &str, status: &JobStatus) -> Result<(), std::io::Error> {
-    let path = get_job_path(job_id);
-    let json = serde_json::to_string(status)?;
-    tokio::fs::write(&path, json).await?;
-    Ok(())
+fn default_chroma_blend() -> f32 {
+    0.1
+}
+
+pub(crate) fn job_key(job_id: &str) -> String {
+    format!("jobs/{}.json", job_id)
+}
+
+pub(crate) fn input_key(job_id: &str) -> String {
+    format!("inputs/{}.tmp", job_id)
+}
+
+pub(crate) fn result_key(job_id: &str) -> String {
+    format!("results/{}.webm", job_id)
+}
+
+pub(crate) fn background_key(job_id: &str) -> String {
+    format!("backgrounds/{}.img", job_id)
 }
 
-async fn load_job_status(job_id: &str) -> Result<Option<JobStatus>, std::io::Error> {
-    let path = get_job_path(job_id);
-    if !Path::new(&path).exists() {
-        return Ok(None);
+/// Reads an entire object out of the store into memory. Only used for the
+/// small JSON status files; media payloads always go through streaming
+/// helpers below instead.
+async fn read_all(store: &Arc<dyn Store>, key: &str) -> io::Result<Vec<u8>> {
+    let mut stream = store.read(key).await?;
+    let mut buf = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        buf.extend_from_slice(&chunk?);
     }
-    match tokio::fs::read_to_string(&path).await {
-        Ok(json) => match serde_json::from_str::<JobStatus>(&json) {
-            Ok(status) => Ok(Some(status)),
-            Err(_) => Ok(None),
-        },
-        Err(_) => Ok(None),
+    Ok(buf)
+}
+
+fn bytes_stream(data: Vec<u8>) -> ByteStream {
+    Box::pin(futures_util::stream::once(async move { Ok(bytes::Bytes::from(data)) }))
+}
+
+pub(crate) async fn save_job_status(store: &Arc<dyn Store>, job_id: &str, status: &JobStatus) -> io::Result<()> {
+    let json = serde_json::to_vec(status)?;
+    store.save_stream(&job_key(job_id), bytes_stream(json)).await
+}
+
+pub(crate) async fn load_job_status(store: &Arc<dyn Store>, job_id: &str) -> io::Result<Option<JobStatus>> {
+    match read_all(store, &job_key(job_id)).await {
+        Ok(json) => Ok(serde_json::from_slice(&json).ok()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
     }
 }
 
-async fn update_job_progress(job_id: &str, progress: u8) -> Result<(), std::io::Error> {
-    if let Some(mut status) = load_job_status(job_id).await? {
+async fn update_job_progress(store: &Arc<dyn Store>, job_id: &str, progress: u8) -> io::Result<()> {
+    if let Some(mut status) = load_job_status(store, job_id).await? {
         status.progress = progress;
-        save_job_status(job_id, &status).await?;
+        save_job_status(store, job_id, &status).await?;
+    }
+    Ok(())
+}
+
+/// Downloads an object from the store into a local scratch file. ffmpeg and
+/// ffprobe need a real path to operate on, so this is the seam between the
+/// store abstraction and the conversion pipeline.
+async fn fetch_to_local(store: &Arc<dyn Store>, key: &str, local_path: &str) -> io::Result<()> {
+    let mut stream = store.read(key).await?;
+    let mut file = tokio::fs::File::create(local_path).await?;
+    while let Some(chunk) = stream.next().await {
+        tokio::io::AsyncWriteExt::write_all(&mut file, &chunk?).await?;
     }
     Ok(())
 }
 
+/// Uploads a local scratch file to the store as a stream, without buffering
+/// it whole in memory.
+async fn push_from_local(store: &Arc<dyn Store>, local_path: &str, key: &str) -> io::Result<()> {
+    let file = tokio::fs::File::open(local_path).await?;
+    let stream: ByteStream = Box::pin(
+        tokio_util::io::ReaderStream::new(file).map(|r| r.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))),
+    );
+    store.save_stream(key, stream).await
+}
+
 #[actix_web::get("/health")]
 async fn health() -> Result<HttpResponse> {
     Ok(HttpResponse::Ok().json(HealthResponse {
@@ -99,69 +188,99 @@ async fn health() -> Result<HttpResponse> {
     }))
 }
 
+/// Streams the multipart `file` field straight into the store under
+/// `input_key(job_id)` and collects the remaining fields into options.
 async fn save_upload(
     mut payload: Multipart,
-) -> Result<(NamedTempFile, ConversionOptions), actix_web::Error> {
-    let mut temp_file = NamedTempFile::new().map_err(|e| {
-        actix_web::error::ErrorInternalServerError(format!("Failed to create temp file: {}", e))
-    })?;
+    store: &Arc<dyn Store>,
+    job_id: &str,
+) -> Result<ConversionOptions, actix_web::Error> {
     let mut options = ConversionOptions {
         crf: default_crf(),
         audio_bitrate: default_audio_bitrate(),
         detect_green: false,
+        green_action: None,
+        chroma_color: None,
+        chroma_similarity: default_chroma_similarity(),
+        chroma_blend: default_chroma_blend(),
+        has_background_image: false,
     };
     while let Some(item) = payload.next().await {
-        let mut field = item?;
+        let field = item?;
         let content_disposition = field.content_disposition();
-        let field_name = content_disposition.get_name().unwrap_or("");
-        match field_name {
+        let field_name = content_disposition.get_name().unwrap_or("").to_string();
+        match field_name.as_str() {
             "file" => {
-                while let Some(chunk) = field.next().await {
-                    let data = chunk?;
-                    temp_file.write_all(&data).map_err(|e| {
-                        actix_web::error::ErrorInternalServerError(format!(
-                            "Failed to write chunk: {}",
-                            e
-                        ))
-                    })?;
-                }
+                let stream: ByteStream = Box::pin(
+                    field.map(|r| r.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))),
+                );
+                store.save_stream(&input_key(job_id), stream).await.map_err(|e| {
+                    actix_web::error::ErrorInternalServerError(format!("Failed to save upload: {}", e))
+                })?;
             }
             "crf" => {
-                let mut value = String::new();
-                while let Some(chunk) = field.next().await {
-                    let data = chunk?;
-                    value.push_str(&String::from_utf8_lossy(&data));
-                }
+                let value = collect_field_text(field).await?;
                 if let Ok(crf) = value.parse::<u8>() {
                     options.crf = crf.clamp(0, 63);
                 }
             }
             "audio_bitrate" => {
-                let mut value = String::new();
-                while let Some(chunk) = field.next().await {
-                    let data = chunk?;
-                    value.push_str(&String::from_utf8_lossy(&data));
-                }
-                options.audio_bitrate = value;
+                options.audio_bitrate = collect_field_text(field).await?;
             }
             "detect_green" => {
-                let mut value = String::new();
-                while let Some(chunk) = field.next().await {
-                    let data = chunk?;
-                    value.push_str(&String::from_utf8_lossy(&data));
-                }
+                let value = collect_field_text(field).await?;
                 options.detect_green = value.trim() == "true";
             }
+            "green_action" => {
+                let value = collect_field_text(field).await?;
+                options.green_action = match value.trim() {
+                    "transparent" | "color" | "image" => Some(value.trim().to_string()),
+                    _ => None,
+                };
+            }
+            "chroma_color" => {
+                options.chroma_color = Some(collect_field_text(field).await?);
+            }
+            "chroma_similarity" => {
+                let value = collect_field_text(field).await?;
+                if let Ok(similarity) = value.parse::<f32>() {
+                    options.chroma_similarity = similarity.clamp(0.0, 1.0);
+                }
+            }
+            "chroma_blend" => {
+                let value = collect_field_text(field).await?;
+                if let Ok(blend) = value.parse::<f32>() {
+                    options.chroma_blend = blend.clamp(0.0, 1.0);
+                }
+            }
+            "background_image" => {
+                let stream: ByteStream = Box::pin(
+                    field.map(|r| r.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))),
+                );
+                store.save_stream(&background_key(job_id), stream).await.map_err(|e| {
+                    actix_web::error::ErrorInternalServerError(format!("Failed to save background image: {}", e))
+                })?;
+                options.has_background_image = true;
+            }
             _ => {}
         }
     }
-    temp_file.flush().map_err(|e| {
-        actix_web::error::ErrorInternalServerError(format!("Failed to flush temp file: {}", e))
-    })?;
-    Ok((temp_file, options))
+    Ok(options)
+}
+
+async fn collect_field_text(mut field: actix_multipart::Field) -> Result<String, actix_web::Error> {
+    let mut value = String::new();
+    while let Some(chunk) = field.next().await {
+        let data = chunk?;
+        value.push_str(&String::from_utf8_lossy(&data));
+    }
+    Ok(value)
 }
 
-async fn detect_green_color(input_path: &str) -> Result<String, std::io::Error> {
+/// Detects the average background color for a chroma-key shot. The `bool`
+/// is confidence: `false` means detection fell back to the default green
+/// without actually sampling pixels, so callers should not chroma-key on it.
+async fn detect_green_color(input_path: &str) -> Result<(String, bool), std::io::Error> {
     let probe_output = Command::new("ffprobe")
         .args(&[
             "-v", "error",
@@ -172,13 +291,13 @@ async fn detect_green_color(input_path: &str) -> Result<String, std::io::Error>
         ])
         .output()
         .await?;
-    
+
     let info = String::from_utf8_lossy(&probe_output.stdout);
     let parts: Vec<&str> = info.trim().split(',').collect();
     if parts.len() < 3 {
-        return Ok("0x00FF00".to_string());
+        return Ok(("0x00FF00".to_string(), false));
     }
-    
+
     let width: i32 = parts[0].parse().unwrap_or(1920);
     let height: i32 = parts[1].parse().unwrap_or(1080);
     let duration: f64 = parts[2].parse().unwrap_or(1.0);
@@ -213,91 +332,119 @@ async fn detect_green_color(input_path: &str) -> Result<String, std::io::Error>
         .stderr(Stdio::null())
         .output()
         .await?;
-    
+
     let mut r_total: u64 = 0;
     let mut g_total: u64 = 0;
     let mut b_total: u64 = 0;
     let mut pixel_count: u64 = 0;
-    
+
     for chunk in output.stdout.chunks_exact(3) {
         r_total += chunk[0] as u64;
         g_total += chunk[1] as u64;
         b_total += chunk[2] as u64;
         pixel_count += 1;
     }
-    
+
     if pixel_count > 0 {
         let r = (r_total / pixel_count) as u8;
         let g = (g_total / pixel_count) as u8;
         let b = (b_total / pixel_count) as u8;
-        return Ok(format!("0x{:02X}{:02X}{:02X}", r, g, b));
+        return Ok((format!("0x{:02X}{:02X}{:02X}", r, g, b), true));
     }
-    
-    Ok("0x00FF00".to_string())
+
+    Ok(("0x00FF00".to_string(), false))
 }
 
 async fn convert_to_webm(
     input_path: &str,
     output_path: &str,
     options: &ConversionOptions,
+    chroma: Option<&chromakey::ChromaKeyPlan>,
     job_id: Option<String>,
+    store: Option<Arc<dyn Store>>,
 ) -> Result<(), std::io::Error> {
     let crf_string = options.crf.to_string();
-    let mut child = Command::new("ffmpeg")
-        .args(&[
-            "-i", input_path,
-            "-c:v", "libvpx-vp9",
-            "-pix_fmt", "yuv420p",
-            "-crf", &crf_string,
-            "-b:v", "1M",
-            "-cpu-used", "5",
-            "-deadline", "realtime",
-            "-row-mt", "1",
-            "-tile-columns", "2",
-            "-threads", "4",
-            "-lag-in-frames", "0",
-            "-c:a", "libopus",
-            "-b:a", &options.audio_bitrate,
-            "-f", "webm",
-            "-y",
-            output_path,
-        ])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?;
-    
-    if let Some(job_id) = job_id {
+    let (chroma_args, extra_input) = match chroma {
+        Some(plan) => plan.ffmpeg_args(),
+        None => (Vec::new(), None),
+    };
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(&["-i", input_path]);
+    if let Some(path) = &extra_input {
+        // The extra input is always a still image (see `ChromaAction::Image`);
+        // without `-loop 1` ffmpeg reads it as a single frame and the
+        // `overlay=shortest=1` filter truncates the whole output to it.
+        cmd.args(&["-loop", "1", "-i", path]);
+    }
+    cmd.args(&["-c:v", "libvpx-vp9"]);
+    if chroma_args.is_empty() {
+        cmd.args(&["-pix_fmt", "yuv420p"]);
+    } else {
+        cmd.args(&chroma_args);
+    }
+    cmd.args(&[
+        "-crf", &crf_string,
+        "-b:v", "1M",
+        "-cpu-used", "5",
+        "-deadline", "realtime",
+        "-row-mt", "1",
+        "-tile-columns", "2",
+        "-threads", "4",
+        "-lag-in-frames", "0",
+        "-c:a", "libopus",
+        "-b:a", &options.audio_bitrate,
+        "-f", "webm",
+        "-y",
+        output_path,
+    ]);
+    let mut child = cmd.stdout(Stdio::null()).stderr(Stdio::null()).spawn()?;
+
+    let deadline = conversion_deadline();
+
+    if let (Some(job_id), Some(store)) = (job_id, store) {
         let duration = get_video_duration(input_path).await.unwrap_or(1.0);
         let output_path_clone = output_path.to_string();
         let job_id_clone = job_id.clone();
-        
+
         tokio::spawn(async move {
             let start = Instant::now();
             let mut last_size = 0u64;
-            
+
             loop {
                 sleep(Duration::from_secs(2)).await;
-                
+
                 if let Ok(metadata) = tokio::fs::metadata(&output_path_clone).await {
                     let current_size = metadata.len();
-                    
+
                     if current_size > 0 && current_size != last_size {
                         let elapsed = start.elapsed().as_secs_f64();
                         let progress = ((elapsed / (duration * 0.8)) * 70.0 + 30.0).min(99.0) as u8;
-                        let _ = update_job_progress(&job_id_clone, progress).await;
+                        let _ = update_job_progress(&store, &job_id_clone, progress).await;
                         last_size = current_size;
                     }
                 }
-                
-                if start.elapsed().as_secs() > 120 {
+
+                if start.elapsed() > deadline {
                     break;
                 }
             }
         });
     }
-    
-    let output = child.wait_with_output().await?;
-    if !output.status.success() {
+
+    let status = tokio::select! {
+        status = child.wait() => status?,
+        _ = sleep(deadline) => {
+            let _ = child.kill().await;
+            let _ = child.wait().await;
+            let _ = tokio::fs::remove_file(output_path).await;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("conversion timed out after {}s", deadline.as_secs()),
+            ));
+        }
+    };
+    if !status.success() {
         return Err(std::io::Error::new(
             std::io::ErrorKind::Other,
             "FFmpeg failed",
@@ -306,52 +453,227 @@ async fn convert_to_webm(
     Ok(())
 }
 
-async fn process_video(
-    job_id: &str,
-    input_path: &str,
-    options: ConversionOptions,
-) {
-    let _ = update_job_progress(job_id, 10).await;
-    
-    let detected_green = if options.detect_green {
-        let _ = update_job_progress(job_id, 20).await;
-        match detect_green_color(input_path).await {
-            Ok(color) => Some(color),
-            Err(_) => None,
+/// Per-job wall-clock cap on ffmpeg, from `CONVERSION_DEADLINE_SECS` or a
+/// 300s default. Guards against a pathological input pinning a worker slot
+/// forever.
+pub(crate) fn conversion_deadline() -> Duration {
+    std::env::var("CONVERSION_DEADLINE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(300))
+}
+
+/// How long a completed job's result stays downloadable before the reaper
+/// reclaims it, from `JOB_TTL_SECS` or a one-hour default.
+pub(crate) fn job_ttl_secs() -> u64 {
+    std::env::var("JOB_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3600)
+}
+
+/// Compares two strings in time independent of where they first differ, so
+/// a timing side-channel can't be used to guess a delete token byte by byte.
+/// Unequal lengths still short-circuit, but that leaks only the length, not
+/// any token content.
+fn tokens_match(provided: &str, actual: &str) -> bool {
+    if provided.len() != actual.len() {
+        return false;
+    }
+    provided
+        .bytes()
+        .zip(actual.bytes())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+pub(crate) fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Runs one job end to end. Called by a worker after it pops `job_id` off
+/// the queue; no separate concurrency permit is taken, the number of
+/// workers is the only cap on concurrent jobs. The job's options were
+/// persisted alongside its status at enqueue time so they survive a
+/// worker-pool restart.
+async fn process_video(store: Arc<dyn Store>, job_id: String) {
+    let job_id = job_id.as_str();
+    let options = match load_job_status(&store, job_id).await {
+        Ok(Some(mut status)) => {
+            status.status = "processing".to_string();
+            status.started_at = Some(now_secs());
+            let options = status.options.clone();
+            let _ = save_job_status(&store, job_id, &status).await;
+            options
+        }
+        _ => return,
+    };
+
+    let _ = update_job_progress(&store, job_id, 10).await;
+
+    let local_input = format!("/tmp/scratch/input_{}.tmp", job_id);
+    let local_output = format!("/tmp/scratch/output_{}.webm", job_id);
+    if let Err(e) = fetch_to_local(&store, &input_key(job_id), &local_input).await {
+        if let Ok(Some(mut status)) = load_job_status(&store, job_id).await {
+            status.status = "failed".to_string();
+            status.error = Some(format!("failed to stage input: {}", e));
+            status.expires_at = Some(now_secs() + job_ttl_secs());
+            let _ = save_job_status(&store, job_id, &status).await;
+        }
+        if options.has_background_image {
+            let _ = store.remove(&background_key(job_id)).await;
+        }
+        return;
+    }
+
+    let media_info = match probe::probe_media(&local_input).await {
+        Ok(info) => info,
+        Err(reason) => {
+            if let Ok(Some(mut status)) = load_job_status(&store, job_id).await {
+                status.status = "failed".to_string();
+                status.error = Some(format!("unsupported or invalid media: {}", reason));
+                status.expires_at = Some(now_secs() + job_ttl_secs());
+                let _ = save_job_status(&store, job_id, &status).await;
+            }
+            let _ = store.remove(&input_key(job_id)).await;
+            let _ = tokio::fs::remove_file(&local_input).await;
+            if options.has_background_image {
+                let _ = store.remove(&background_key(job_id)).await;
+            }
+            return;
+        }
+    };
+    if let Ok(Some(mut status)) = load_job_status(&store, job_id).await {
+        status.width = Some(media_info.width);
+        status.height = Some(media_info.height);
+        status.duration = Some(media_info.duration);
+        status.codec = Some(media_info.codec.clone());
+        let _ = save_job_status(&store, job_id, &status).await;
+    }
+
+    let (detected_green, green_confident) = if options.detect_green {
+        let _ = update_job_progress(&store, job_id, 20).await;
+        match detect_green_color(&local_input).await {
+            Ok((color, confident)) => (Some(color), confident),
+            Err(_) => (None, false),
         }
     } else {
-        None
+        (None, false)
     };
-    
-    if let Ok(Some(mut status)) = load_job_status(job_id).await {
+
+    if let Ok(Some(mut status)) = load_job_status(&store, job_id).await {
         status.progress = 30;
         status.detected_green = detected_green.clone();
-        let _ = save_job_status(job_id, &status).await;
+        let _ = save_job_status(&store, job_id, &status).await;
+    }
+
+    let local_background = format!("/tmp/scratch/bg_{}.img", job_id);
+    let chroma_plan = match (&detected_green, &options.green_action) {
+        (Some(color), Some(action)) if green_confident => {
+            let resolved_action = match action.as_str() {
+                "transparent" => Some(chromakey::ChromaAction::Transparent),
+                "color" => Some(chromakey::ChromaAction::Color(
+                    options.chroma_color.clone().unwrap_or_else(|| "0x000000".to_string()),
+                )),
+                "image" if options.has_background_image => {
+                    match fetch_to_local(&store, &background_key(job_id), &local_background).await {
+                        Ok(()) => Some(chromakey::ChromaAction::Image(local_background.clone())),
+                        Err(_) => None,
+                    }
+                }
+                _ => None,
+            };
+            resolved_action.map(|action| chromakey::ChromaKeyPlan {
+                color: color.clone(),
+                similarity: options.chroma_similarity,
+                blend: options.chroma_blend,
+                action,
+                width: media_info.width,
+                height: media_info.height,
+            })
+        }
+        _ => None,
+    };
+
+    if let Ok(Some(mut status)) = load_job_status(&store, job_id).await {
+        status.chroma_key_applied = chroma_plan.is_some();
+        let _ = save_job_status(&store, job_id, &status).await;
     }
-    
-    let output_path = format!("/tmp/results/{}.webm", job_id);
+
     let job_id_for_ffmpeg = job_id.to_string();
-    
-    match convert_to_webm(input_path, &output_path, &options, Some(job_id_for_ffmpeg)).await {
+    let conversion_started = Instant::now();
+    let conversion_result = convert_to_webm(
+        &local_input,
+        &local_output,
+        &options,
+        chroma_plan.as_ref(),
+        Some(job_id_for_ffmpeg),
+        Some(store.clone()),
+    )
+    .await;
+    metrics::CONVERSION_DURATION_SECONDS.observe(conversion_started.elapsed().as_secs_f64());
+
+    match conversion_result {
         Ok(_) => {
-            if let Ok(Some(mut status)) = load_job_status(job_id).await {
-                status.status = "complete".to_string();
-                status.progress = 100;
-                status.result_path = Some(output_path);
-                status.detected_green = detected_green;
-                let _ = save_job_status(job_id, &status).await;
+            let key = result_key(job_id);
+            match push_from_local(&store, &local_output, &key).await {
+                Ok(()) => {
+                    if let Ok(Some(mut status)) = load_job_status(&store, job_id).await {
+                        status.status = "complete".to_string();
+                        status.progress = 100;
+                        status.result_key = Some(key);
+                        status.detected_green = detected_green;
+                        status.expires_at = Some(now_secs() + job_ttl_secs());
+                        let _ = save_job_status(&store, job_id, &status).await;
+                    }
+                    metrics::CONVERSIONS_SUCCEEDED_TOTAL.inc();
+                    if let (Ok(input_meta), Ok(output_meta)) = (
+                        tokio::fs::metadata(&local_input).await,
+                        tokio::fs::metadata(&local_output).await,
+                    ) {
+                        if input_meta.len() > 0 {
+                            metrics::OUTPUT_INPUT_SIZE_RATIO
+                                .observe(output_meta.len() as f64 / input_meta.len() as f64);
+                        }
+                    }
+                }
+                Err(e) => {
+                    if let Ok(Some(mut status)) = load_job_status(&store, job_id).await {
+                        status.status = "failed".to_string();
+                        status.error = Some(format!("failed to store result: {}", e));
+                        status.expires_at = Some(now_secs() + job_ttl_secs());
+                        let _ = save_job_status(&store, job_id, &status).await;
+                    }
+                    metrics::CONVERSIONS_FAILED_TOTAL.inc();
+                }
             }
         }
         Err(e) => {
-            if let Ok(Some(mut status)) = load_job_status(job_id).await {
+            if e.kind() == std::io::ErrorKind::TimedOut {
+                metrics::CONVERSIONS_TIMED_OUT_TOTAL.inc();
+            } else {
+                metrics::CONVERSIONS_FAILED_TOTAL.inc();
+            }
+            if let Ok(Some(mut status)) = load_job_status(&store, job_id).await {
                 status.status = "failed".to_string();
                 status.error = Some(e.to_string());
-                let _ = save_job_status(job_id, &status).await;
+                status.expires_at = Some(now_secs() + job_ttl_secs());
+                let _ = save_job_status(&store, job_id, &status).await;
             }
         }
     }
-    
-    let _ = tokio::fs::remove_file(input_path).await;
+
+    let _ = store.remove(&input_key(job_id)).await;
+    if options.has_background_image {
+        let _ = store.remove(&background_key(job_id)).await;
+        let _ = tokio::fs::remove_file(&local_background).await;
+    }
+    let _ = tokio::fs::remove_file(&local_input).await;
+    let _ = tokio::fs::remove_file(&local_output).await;
 }
 
 async fn get_video_duration(input_path: &str) -> Result<f64, std::io::Error> {
@@ -378,52 +700,77 @@ async fn get_video_duration(input_path: &str) -> Result<f64, std::io::Error> {
 async fn upload_video(
     req: actix_web::HttpRequest,
     payload: Multipart,
+    store: web::Data<Arc<dyn Store>>,
+    queue: web::Data<Arc<JobQueue>>,
 ) -> Result<HttpResponse> {
-    let start_time = Instant::now();
     let job_id = req
         .headers()
         .get("X-Job-Id")
         .and_then(|h| h.to_str().ok())
         .map(|s| s.to_string())
         .unwrap_or_else(|| Uuid::new_v4().to_string());
-    let (temp_file, options) = save_upload(payload).await?;
-    let persistent_path = format!("/tmp/inputs/input_{}.tmp", job_id);
-    tokio::fs::copy(temp_file.path(), &persistent_path)
-        .await
-        .map_err(|e| {
-            actix_web::error::ErrorInternalServerError(format!("Failed to save file: {}", e))
-        })?;
+    let options = save_upload(payload, &store, &job_id).await?;
+    if options.green_action.is_some() && !options.detect_green {
+        let _ = store.remove(&input_key(&job_id)).await;
+        if options.has_background_image {
+            let _ = store.remove(&background_key(&job_id)).await;
+        }
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "green_action requires detect_green=true"
+        })));
+    }
+    let delete_token = Uuid::new_v4().to_string();
     let job_status = JobStatus {
-        status: "processing".to_string(),
-        progress: 5,
-        result_path: None,
+        status: "queued".to_string(),
+        progress: 0,
+        result_key: None,
         detected_green: None,
+        chroma_key_applied: false,
         error: None,
+        options,
+        width: None,
+        height: None,
+        duration: None,
+        codec: None,
+        delete_token: delete_token.clone(),
+        expires_at: None,
+        started_at: None,
     };
-    save_job_status(&job_id, &job_status).await.map_err(|e| {
+    save_job_status(&store, &job_id, &job_status).await.map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Failed to create job: {}", e))
     })?;
-    let job_id_response = job_id.clone();
-    tokio::spawn(async move {
-        process_video(&job_id, &persistent_path, options).await;
-    });
+    let position = queue.push(job_id.clone()).await;
+    metrics::UPLOADS_TOTAL.inc();
     Ok(HttpResponse::Ok().json(UploadResponse {
-        job_id: job_id_response,
-        status: "processing".to_string(),
+        job_id,
+        status: "queued".to_string(),
+        queue_position: Some(position),
+        delete_token,
     }))
 }
 
 #[actix_web::get("/status/{job_id}")]
 async fn check_status(
     job_id: web::Path<String>,
+    store: web::Data<Arc<dyn Store>>,
+    queue: web::Data<Arc<JobQueue>>,
 ) -> Result<HttpResponse> {
-    match load_job_status(job_id.as_str()).await {
-        Ok(Some(job)) => Ok(HttpResponse::Ok().json(StatusResponse {
-            status: job.status,
-            progress: job.progress,
-            detected_green: job.detected_green,
-            error: job.error,
-        })),
+    match load_job_status(&store, job_id.as_str()).await {
+        Ok(Some(job)) => {
+            let queue_position = queue.position_of(job_id.as_str()).await;
+            Ok(HttpResponse::Ok().json(StatusResponse {
+                status: job.status,
+                progress: job.progress,
+                queue_position,
+                detected_green: job.detected_green,
+                chroma_key_applied: job.chroma_key_applied,
+                error: job.error,
+                width: job.width,
+                height: job.height,
+                duration: job.duration,
+                codec: job.codec,
+            }))
+        }
         Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
             "error": "Job not found"
         }))),
@@ -435,41 +782,122 @@ async fn check_status(
 
 #[actix_web::get("/download/{job_id}")]
 async fn download_result(
+    req: actix_web::HttpRequest,
     job_id: web::Path<String>,
+    store: web::Data<Arc<dyn Store>>,
 ) -> Result<HttpResponse> {
-    match load_job_status(job_id.as_str()).await {
+    match load_job_status(&store, job_id.as_str()).await {
         Ok(Some(job)) => {
-            if job.status == "complete" {
-                if let Some(path) = &job.result_path {
-                    match tokio::fs::read(path).await {
-                        Ok(data) => {
-                            let path_clone = path.clone();
-                            let job_id_clone = job_id.to_string();
-                            tokio::spawn(async move {
-                                let _ = tokio::fs::remove_file(&path_clone).await;
-                                let _ = tokio::fs::remove_file(&get_job_path(&job_id_clone)).await;
-                            });
-                            let mut builder = HttpResponse::Ok();
-                            builder.content_type("video/webm");
-                            if let Some(green) = &job.detected_green {
-                                builder.insert_header(("X-Detected-Green", green.clone()));
-                            }
-                            return Ok(builder.body(data));
-                        }
-                        Err(e) => {
-                            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                                "error": "Failed to read result file",
-                                "details": e.to_string()
-                            })));
-                        }
-                    }
+            let key = match (&job.status == "complete", &job.result_key) {
+                (true, Some(key)) => key.clone(),
+                _ => {
+                    return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                        "error": "Video not ready",
+                        "status": job.status,
+                        "progress": job.progress
+                    })));
+                }
+            };
+            let size = match store.size(&key).await {
+                Ok(size) => size,
+                Err(e) => {
+                    return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                        "error": "Failed to read result file",
+                        "details": e.to_string()
+                    })));
                 }
+            };
+            let range = req
+                .headers()
+                .get("Range")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| parse_range(h, size));
+            let (start, end, partial) = match range {
+                Some((start, end)) => (start, end, true),
+                None => (0, size.saturating_sub(1), false),
+            };
+            let body = store.read_range(&key, start, end).await.map_err(|e| {
+                actix_web::error::ErrorInternalServerError(format!("Failed to read result: {}", e))
+            })?;
+            let mut builder = if partial {
+                HttpResponse::PartialContent()
+            } else {
+                HttpResponse::Ok()
+            };
+            builder.content_type("video/webm");
+            builder.insert_header(("Accept-Ranges", "bytes"));
+            builder.insert_header(("Content-Length", (end - start + 1).to_string()));
+            if partial {
+                builder.insert_header(("Content-Range", format!("bytes {}-{}/{}", start, end, size)));
+            }
+            if let Some(green) = &job.detected_green {
+                builder.insert_header(("X-Detected-Green", green.clone()));
+            }
+            Ok(builder.streaming(
+                body.map(|chunk| chunk.map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))),
+            ))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Job not found"
+        }))),
+        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "Failed to load job"
+        }))),
+    }
+}
+
+/// Parses a single-range `Range: bytes=start-end` header against a known
+/// object size. For a multi-range request only the first range is used.
+fn parse_range(header: &str, size: u64) -> Option<(u64, u64)> {
+    let spec = header.strip_prefix("bytes=")?.split(',').next()?.trim();
+    let (start_str, end_str) = spec.split_once('-')?;
+    let (start, end) = if start_str.is_empty() {
+        let suffix_len: u64 = end_str.parse().ok()?;
+        (size.saturating_sub(suffix_len), size.saturating_sub(1))
+    } else {
+        let start: u64 = start_str.parse().ok()?;
+        let end = if end_str.is_empty() {
+            size.saturating_sub(1)
+        } else {
+            end_str.parse::<u64>().ok()?.min(size.saturating_sub(1))
+        };
+        (start, end)
+    };
+    if start > end || start >= size {
+        return None;
+    }
+    Some((start, end))
+}
+
+#[actix_web::delete("/job/{job_id}")]
+async fn delete_job(
+    req: actix_web::HttpRequest,
+    job_id: web::Path<String>,
+    store: web::Data<Arc<dyn Store>>,
+) -> Result<HttpResponse> {
+    let provided_token = req
+        .headers()
+        .get("X-Delete-Token")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string())
+        .or_else(|| {
+            web::Query::<std::collections::HashMap<String, String>>::from_query(req.query_string())
+                .ok()
+                .and_then(|q| q.get("token").cloned())
+        });
+
+    match load_job_status(&store, job_id.as_str()).await {
+        Ok(Some(job)) => {
+            if !provided_token
+                .as_deref()
+                .is_some_and(|token| tokens_match(token, &job.delete_token))
+            {
+                return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+                    "error": "Invalid or missing delete token"
+                })));
             }
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "Video not ready",
-                "status": job.status,
-                "progress": job.progress
-            })))
+            reaper::purge_job(&store, job_id.as_str(), &job).await;
+            Ok(HttpResponse::Ok().json(serde_json::json!({ "deleted": job_id.as_str() })))
         }
         Ok(None) => Ok(HttpResponse::NotFound().json(serde_json::json!({
             "error": "Job not found"
@@ -480,24 +908,134 @@ async fn download_result(
     }
 }
 
+#[actix_web::get("/metrics")]
+async fn metrics_endpoint(queue: web::Data<Arc<JobQueue>>) -> HttpResponse {
+    metrics::QUEUE_DEPTH.set(queue.len().await as i64);
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render())
+}
+
+/// Scans `jobs/` for anything left `"queued"` or `"processing"` from before
+/// a restart. Queued jobs are safe to requeue as-is; a job caught mid
+/// conversion can't be resumed (the ffmpeg child is gone), so it's marked
+/// failed instead of silently orphaned.
+async fn requeue_unfinished_jobs(store: &Arc<dyn Store>, queue: &JobQueue) -> io::Result<()> {
+    for key in store.list("jobs").await? {
+        let job_id = match key.rsplit('/').next().and_then(|name| name.strip_suffix(".json")) {
+            Some(job_id) => job_id.to_string(),
+            None => continue,
+        };
+        if let Some(mut status) = load_job_status(store, &job_id).await? {
+            match status.status.as_str() {
+                "queued" => {
+                    queue.push(job_id).await;
+                }
+                "processing" => {
+                    status.status = "failed".to_string();
+                    status.error = Some("interrupted by server restart".to_string());
+                    status.expires_at = Some(now_secs() + job_ttl_secs());
+                    save_job_status(store, &job_id, &status).await?;
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    std::fs::create_dir_all("/tmp/jobs")?;
-    std::fs::create_dir_all("/tmp/inputs")?;
-    std::fs::create_dir_all("/tmp/results")?;
+    std::fs::create_dir_all("/tmp/scratch")?;
+    let store = store::from_env().await?;
+    let worker_count = queue::worker_count_from_env();
+    let queue = Arc::new(JobQueue::new());
+
+    requeue_unfinished_jobs(&store, &queue).await?;
+
+    queue::spawn_workers(queue.clone(), worker_count, {
+        let store = store.clone();
+        move |job_id: String| {
+            let store = store.clone();
+            async move {
+                metrics::ACTIVE_WORKERS.inc();
+                process_video(store, job_id).await;
+                metrics::ACTIVE_WORKERS.dec();
+            }
+        }
+    });
+
+    reaper::spawn(store.clone());
+
     let port = std::env::var("PORT")
         .unwrap_or_else(|_| "8666".to_string())
         .parse::<u16>()
         .unwrap_or(8666);
     HttpServer::new(move || {
         App::new()
+            .app_data(web::Data::new(store.clone()))
+            .app_data(web::Data::new(queue.clone()))
             .service(health)
             .service(upload_video)
             .service(check_status)
             .service(download_result)
+            .service(delete_job)
+            .service(metrics_endpoint)
     })
     .workers(4)
     .bind(("0.0.0.0", port))?
     .run()
     .await
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_range;
+
+    #[test]
+    fn full_range() {
+        assert_eq!(parse_range("bytes=0-499", 1000), Some((0, 499)));
+    }
+
+    #[test]
+    fn open_ended_range() {
+        assert_eq!(parse_range("bytes=500-", 1000), Some((500, 999)));
+    }
+
+    #[test]
+    fn suffix_range() {
+        assert_eq!(parse_range("bytes=-500", 1000), Some((500, 999)));
+    }
+
+    #[test]
+    fn suffix_range_longer_than_size_clamps_to_start() {
+        assert_eq!(parse_range("bytes=-5000", 1000), Some((0, 999)));
+    }
+
+    #[test]
+    fn zero_length_suffix_is_rejected() {
+        // start == size - 1 + 1 == size, so start > end; must not underflow.
+        assert_eq!(parse_range("bytes=-0", 1000), None);
+    }
+
+    #[test]
+    fn end_beyond_size_is_clamped() {
+        assert_eq!(parse_range("bytes=0-999999", 1000), Some((0, 999)));
+    }
+
+    #[test]
+    fn start_beyond_size_is_rejected() {
+        assert_eq!(parse_range("bytes=1000-1001", 1000), None);
+    }
+
+    #[test]
+    fn malformed_header_is_rejected() {
+        assert_eq!(parse_range("bytes=abc-def", 1000), None);
+        assert_eq!(parse_range("not-a-range", 1000), None);
+    }
+
+    #[test]
+    fn zero_size_object_has_no_valid_range() {
+        assert_eq!(parse_range("bytes=0-0", 0), None);
+    }
+}
@@ -0,0 +1,66 @@
+use once_cell::sync::Lazy;
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Opts, Registry, TextEncoder};
+
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+fn register_counter(name: &str, help: &str) -> IntCounter {
+    let counter = IntCounter::with_opts(Opts::new(name, help)).unwrap();
+    REGISTRY.register(Box::new(counter.clone())).unwrap();
+    counter
+}
+
+fn register_gauge(name: &str, help: &str) -> IntGauge {
+    let gauge = IntGauge::with_opts(Opts::new(name, help)).unwrap();
+    REGISTRY.register(Box::new(gauge.clone())).unwrap();
+    gauge
+}
+
+fn register_histogram(name: &str, help: &str, buckets: Vec<f64>) -> Histogram {
+    let histogram = Histogram::with_opts(HistogramOpts::new(name, help).buckets(buckets)).unwrap();
+    REGISTRY.register(Box::new(histogram.clone())).unwrap();
+    histogram
+}
+
+pub static UPLOADS_TOTAL: Lazy<IntCounter> =
+    Lazy::new(|| register_counter("webm_uploads_total", "Total uploads accepted"));
+
+pub static CONVERSIONS_SUCCEEDED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    register_counter("webm_conversions_succeeded_total", "Conversions that finished successfully")
+});
+
+pub static CONVERSIONS_FAILED_TOTAL: Lazy<IntCounter> =
+    Lazy::new(|| register_counter("webm_conversions_failed_total", "Conversions that failed"));
+
+pub static CONVERSIONS_TIMED_OUT_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    register_counter("webm_conversions_timed_out_total", "Conversions killed for exceeding the deadline")
+});
+
+pub static CONVERSION_DURATION_SECONDS: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram(
+        "webm_conversion_duration_seconds",
+        "Wall-clock time spent running ffmpeg per job",
+        vec![1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0],
+    )
+});
+
+pub static OUTPUT_INPUT_SIZE_RATIO: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram(
+        "webm_output_input_size_ratio",
+        "Converted output size divided by input size",
+        vec![0.1, 0.25, 0.5, 0.75, 1.0, 1.5, 2.0],
+    )
+});
+
+pub static QUEUE_DEPTH: Lazy<IntGauge> =
+    Lazy::new(|| register_gauge("webm_queue_depth", "Jobs currently waiting to be processed"));
+
+pub static ACTIVE_WORKERS: Lazy<IntGauge> =
+    Lazy::new(|| register_gauge("webm_active_workers", "Worker slots currently running ffmpeg"));
+
+/// Renders every registered metric in Prometheus text exposition format.
+pub fn render() -> String {
+    let metric_families = REGISTRY.gather();
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&metric_families, &mut buffer).unwrap();
+    String::from_utf8(buffer).unwrap_or_default()
+}
@@ -0,0 +1,78 @@
+use serde::Serialize;
+use serde_json::Value;
+use tokio::process::Command;
+
+const RECOGNIZED_VIDEO_CODECS: &[&str] = &[
+    "h264", "hevc", "vp8", "vp9", "av1", "mpeg4", "mpeg2video", "mjpeg", "prores",
+];
+
+const MAX_DIMENSION: i64 = 8192;
+
+#[derive(Clone, Serialize)]
+pub struct MediaInfo {
+    pub width: i64,
+    pub height: i64,
+    pub duration: f64,
+    pub codec: String,
+}
+
+/// Runs `ffprobe` over `input_path` and validates that it describes a
+/// decodable video with a recognized codec, sane dimensions, and a finite
+/// duration, so a non-video, corrupt, or hostile upload is rejected before
+/// it ever reaches ffmpeg. Returns a human-readable rejection reason.
+pub async fn probe_media(input_path: &str) -> Result<MediaInfo, String> {
+    let output = Command::new("ffprobe")
+        .args(&[
+            "-v", "error",
+            "-show_streams",
+            "-show_format",
+            "-of", "json",
+            input_path,
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("ffprobe failed to run: {}", e))?;
+
+    if !output.status.success() {
+        return Err("ffprobe could not read the file".to_string());
+    }
+
+    let parsed: Value =
+        serde_json::from_slice(&output.stdout).map_err(|e| format!("ffprobe produced invalid json: {}", e))?;
+
+    let streams = parsed["streams"].as_array().cloned().unwrap_or_default();
+    let video_stream = streams
+        .iter()
+        .find(|s| s["codec_type"].as_str() == Some("video"))
+        .ok_or_else(|| "no decodable video stream found".to_string())?;
+
+    let codec = video_stream["codec_name"]
+        .as_str()
+        .ok_or_else(|| "video stream has no codec name".to_string())?
+        .to_string();
+    if !RECOGNIZED_VIDEO_CODECS.contains(&codec.as_str()) {
+        return Err(format!("unrecognized video codec '{}'", codec));
+    }
+
+    let width = video_stream["width"].as_i64().unwrap_or(0);
+    let height = video_stream["height"].as_i64().unwrap_or(0);
+    if width <= 0 || height <= 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(format!("implausible dimensions {}x{}", width, height));
+    }
+
+    let duration: f64 = parsed["format"]["duration"]
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .or_else(|| video_stream["duration"].as_str().and_then(|s| s.parse().ok()))
+        .unwrap_or(f64::NAN);
+    if !duration.is_finite() || duration <= 0.0 {
+        return Err("media has no finite duration".to_string());
+    }
+
+    Ok(MediaInfo {
+        width,
+        height,
+        duration,
+        codec,
+    })
+}
@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, Notify};
+
+/// In-process FIFO of job ids waiting to be processed. Job membership lives
+/// in the `Store` as the "queued"/"processing" status on each job, so a
+/// restart rebuilds this queue by scanning the store rather than relying on
+/// any state kept only in memory.
+pub struct JobQueue {
+    jobs: Mutex<VecDeque<String>>,
+    notify: Notify,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self {
+            jobs: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+        }
+    }
+
+    pub async fn push(&self, job_id: String) -> usize {
+        let mut jobs = self.jobs.lock().await;
+        jobs.push_back(job_id);
+        let position = jobs.len();
+        self.notify.notify_one();
+        position
+    }
+
+    pub async fn position_of(&self, job_id: &str) -> Option<usize> {
+        let jobs = self.jobs.lock().await;
+        jobs.iter().position(|id| id == job_id).map(|i| i + 1)
+    }
+
+    pub async fn len(&self) -> usize {
+        self.jobs.lock().await.len()
+    }
+
+    async fn pop(&self) -> String {
+        loop {
+            {
+                let mut jobs = self.jobs.lock().await;
+                if let Some(job_id) = jobs.pop_front() {
+                    return job_id;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// Number of concurrent worker tasks to run, from `WORKER_COUNT` or the
+/// number of available CPUs.
+pub fn worker_count_from_env() -> usize {
+    std::env::var("WORKER_COUNT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+}
+
+/// Spawns `worker_count` tasks that each loop: pop a job id off `queue`,
+/// then run it to completion. Each loop processes at most one job at a
+/// time, so `worker_count` alone is the concurrency cap; there's no
+/// separate permit pool to keep in sync with it.
+pub fn spawn_workers<F, Fut>(queue: Arc<JobQueue>, worker_count: usize, run_job: F)
+where
+    F: Fn(String) -> Fut + Send + Sync + Clone + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    for _ in 0..worker_count {
+        let queue = queue.clone();
+        let run_job = run_job.clone();
+        tokio::spawn(async move {
+            loop {
+                let job_id = queue.pop().await;
+                run_job(job_id).await;
+            }
+        });
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::sleep;
+
+use crate::store::Store;
+use crate::{
+    background_key, conversion_deadline, input_key, job_key, load_job_status, now_secs, result_key, JobStatus,
+};
+
+/// Removes every object a job owns: its input (if still staged), its
+/// background image (if one was uploaded), its result (if it completed),
+/// and the status record itself.
+pub(crate) async fn purge_job(store: &Arc<dyn Store>, job_id: &str, job: &JobStatus) {
+    let _ = store.remove(&input_key(job_id)).await;
+    if job.options.has_background_image {
+        let _ = store.remove(&background_key(job_id)).await;
+    }
+    if job.result_key.is_some() {
+        let _ = store.remove(&result_key(job_id)).await;
+    }
+    let _ = store.remove(&job_key(job_id)).await;
+}
+
+fn reap_interval() -> Duration {
+    std::env::var("REAPER_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(60))
+}
+
+/// Spawns a periodic background task that reclaims disk from jobs whose
+/// result has expired, or that have been stuck `"processing"` long past
+/// the conversion deadline (a crashed worker that never got to mark the
+/// job failed).
+pub fn spawn(store: Arc<dyn Store>) {
+    tokio::spawn(async move {
+        let interval = reap_interval();
+        let stuck_after = conversion_deadline() * 2;
+        loop {
+            sleep(interval).await;
+            let now = now_secs();
+            let keys = match store.list("jobs").await {
+                Ok(keys) => keys,
+                Err(_) => continue,
+            };
+            for key in keys {
+                let job_id = match key.rsplit('/').next().and_then(|name| name.strip_suffix(".json")) {
+                    Some(job_id) => job_id.to_string(),
+                    None => continue,
+                };
+                let job = match load_job_status(&store, &job_id).await {
+                    Ok(Some(job)) => job,
+                    _ => continue,
+                };
+                let expired = job.expires_at.map(|expires_at| now >= expires_at).unwrap_or(false);
+                let stuck = job.status == "processing"
+                    && job
+                        .started_at
+                        .map(|started_at| now.saturating_sub(started_at) > stuck_after.as_secs())
+                        .unwrap_or(false);
+                if expired || stuck {
+                    purge_job(&store, &job_id, &job).await;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,260 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use aws_sdk_s3 as s3;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use tokio_util::io::{ReaderStream, StreamReader};
+
+pub type ByteStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// Abstracts over where uploads, conversion results, and job-status files
+/// live so a `/status` on one node can serve a `/download` on another.
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn save_stream(&self, key: &str, stream: ByteStream) -> io::Result<()>;
+    async fn read(&self, key: &str) -> io::Result<ByteStream>;
+    async fn remove(&self, key: &str) -> io::Result<()>;
+    /// Lists keys under `prefix`, used to rediscover in-flight jobs on restart.
+    async fn list(&self, prefix: &str) -> io::Result<Vec<String>>;
+    /// Size in bytes, used to answer `Content-Length` without buffering.
+    async fn size(&self, key: &str) -> io::Result<u64>;
+    /// Reads the byte range `[start, end]` (both ends inclusive), for
+    /// `Range` request support.
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> io::Result<ByteStream>;
+}
+
+/// Current behavior: everything lives under a root directory on local disk.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+#[async_trait]
+impl Store for LocalStore {
+    async fn save_stream(&self, key: &str, mut stream: ByteStream) -> io::Result<()> {
+        let path = self.path_for(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::File::create(&path).await?;
+        while let Some(chunk) = stream.next().await {
+            tokio::io::AsyncWriteExt::write_all(&mut file, &chunk?).await?;
+        }
+        Ok(())
+    }
+
+    async fn read(&self, key: &str) -> io::Result<ByteStream> {
+        let path = self.path_for(key);
+        if !Path::new(&path).exists() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "object not found"));
+        }
+        let file = tokio::fs::File::open(&path).await?;
+        Ok(Box::pin(ReaderStream::new(file)))
+    }
+
+    async fn remove(&self, key: &str) -> io::Result<()> {
+        let path = self.path_for(key);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> io::Result<Vec<String>> {
+        let dir = self.path_for(prefix);
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut keys = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(name) = entry.file_name().to_str() {
+                keys.push(format!("{}/{}", prefix.trim_end_matches('/'), name));
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn size(&self, key: &str) -> io::Result<u64> {
+        let metadata = tokio::fs::metadata(self.path_for(key)).await?;
+        Ok(metadata.len())
+    }
+
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> io::Result<ByteStream> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let path = self.path_for(key);
+        if !Path::new(&path).exists() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "object not found"));
+        }
+        let mut file = tokio::fs::File::open(&path).await?;
+        file.seek(io::SeekFrom::Start(start)).await?;
+        let len = end.saturating_sub(start) + 1;
+        Ok(Box::pin(ReaderStream::new(file.take(len))))
+    }
+}
+
+/// S3-compatible backend (AWS, MinIO, Garage, ...) selected via env vars.
+pub struct S3Store {
+    client: s3::Client,
+    bucket: String,
+}
+
+impl S3Store {
+    pub async fn from_env() -> io::Result<Self> {
+        let bucket = std::env::var("S3_BUCKET")
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "S3_BUCKET is required"))?;
+        let region = std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(s3::config::Region::new(region));
+        if let Ok(endpoint) = std::env::var("S3_ENDPOINT") {
+            loader = loader.endpoint_url(endpoint);
+        }
+        if let (Ok(access_key), Ok(secret_key)) = (
+            std::env::var("S3_ACCESS_KEY"),
+            std::env::var("S3_SECRET_KEY"),
+        ) {
+            loader = loader.credentials_provider(s3::config::Credentials::new(
+                access_key,
+                secret_key,
+                None,
+                None,
+                "webm-converter-env",
+            ));
+        }
+        let config = loader.load().await;
+
+        // MinIO/Garage need path-style addressing; AWS proper does not.
+        let path_style = std::env::var("S3_ENDPOINT").is_ok();
+        let s3_config = s3::config::Builder::from(&config)
+            .force_path_style(path_style)
+            .build();
+
+        Ok(Self {
+            client: s3::Client::from_conf(s3_config),
+            bucket,
+        })
+    }
+}
+
+#[async_trait]
+impl Store for S3Store {
+    async fn save_stream(&self, key: &str, stream: ByteStream) -> io::Result<()> {
+        let reader = StreamReader::new(stream);
+        let body = s3::primitives::ByteStream::read_from()
+            .reader(reader)
+            .build()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        Ok(())
+    }
+
+    async fn read(&self, key: &str) -> io::Result<ByteStream> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e.to_string()))?;
+        let reader = tokio_util::compat::FuturesAsyncReadCompatExt::compat(output.body.into_async_read());
+        Ok(Box::pin(ReaderStream::new(reader)))
+    }
+
+    async fn remove(&self, key: &str) -> io::Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> io::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let mut request = self.client.list_objects_v2().bucket(&self.bucket).prefix(prefix);
+            if let Some(token) = continuation_token.take() {
+                request = request.continuation_token(token);
+            }
+            let output = request
+                .send()
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            for object in output.contents() {
+                if let Some(key) = object.key() {
+                    keys.push(key.to_string());
+                }
+            }
+            match output.next_continuation_token() {
+                Some(token) => continuation_token = Some(token.to_string()),
+                None => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn size(&self, key: &str) -> io::Result<u64> {
+        let output = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e.to_string()))?;
+        Ok(output.content_length().unwrap_or(0) as u64)
+    }
+
+    async fn read_range(&self, key: &str, start: u64, end: u64) -> io::Result<ByteStream> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .range(format!("bytes={}-{}", start, end))
+            .send()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e.to_string()))?;
+        let reader = tokio_util::compat::FuturesAsyncReadCompatExt::compat(output.body.into_async_read());
+        Ok(Box::pin(ReaderStream::new(reader)))
+    }
+}
+
+/// Picks the backend based on `STORE_BACKEND` (`local` by default, or `s3`).
+pub async fn from_env() -> io::Result<Arc<dyn Store>> {
+    match std::env::var("STORE_BACKEND").unwrap_or_else(|_| "local".to_string()).as_str() {
+        "s3" => Ok(Arc::new(S3Store::from_env().await?)),
+        _ => Ok(Arc::new(LocalStore::new(
+            std::env::var("STORE_LOCAL_ROOT").unwrap_or_else(|_| "/tmp".to_string()),
+        ))),
+    }
+}